@@ -1,13 +1,20 @@
 extern mod native;  // TO start a native thread
 extern mod rsfml;   // Multimedia library
+extern mod gilrs;   // Gamepad input
+#[cfg(feature = "scripting")]
+extern mod lua;     // Lua paddle AI / rule scripting
 
 use rsfml::window::{ContextSettings, VideoMode, event, keyboard, Close};
-use rsfml::graphics::{RenderWindow, Color, Texture, Sprite, IntRect, CircleShape};
+use rsfml::graphics::{RenderWindow, Color, Texture, Sprite, IntRect, CircleShape, Font, Text};
 use rsfml::system::vector2::Vector2f;
+use rsfml::system::clock::Clock;
 
 use std::hashmap::HashMap;
 use std::num::FromPrimitive;
 use std::rand::{task_rng, Rng};
+use std::comm::{Port, Chan, stream};
+use std::task;
+use std::mem;
 
 // Window Defaults
 static WINDOW_WIDTH:  uint = 1024;
@@ -17,12 +24,31 @@ static PADDLE_WIDTH:  i32 = 20;  // tyeps are weird due to RSFML binding.
 static PADDLE_HEIGHT: i32 = 50;  // tyeps are weird due to RSFML binding.
 
 // Game Option Defaults
+// Set to true to drive this client off an authoritative net::spawn_server()
+// (see main()) instead of integrating input/physics locally.
+static NETWORKED_PLAY: bool = false;
+// Set to true to hand the non-local paddle(s) over to ./assets/ai.lua
+// instead of leaving them uncontrolled. Requires the "scripting" feature.
+static AI_ENABLED: bool = false;
+static AI_SCRIPT_PATH: &'static str = "./assets/ai.lua";
+
+// Target simulation rate for the fixed-timestep accumulator loop in main().
+// from_previous() runs physics/input in increments of exactly FIXED_DT
+// regardless of the render frame rate, so gameplay stays deterministic.
+static TICK_RATE:      f32 = 60.;
+static FIXED_DT:       f32 = 1. / TICK_RATE;
+// Clamp a single real frame's delta before feeding the accumulator, so a
+// debugger pause or a slow frame can't cause a runaway catch-up spiral.
+static MAX_FRAME_TIME: f32 = 0.25;
+
 static PADDLE_PADDING:     f32 = 30.;
 static LHS_START_POS_X:    f32 = 0. + PADDLE_PADDING;
 static RHS_START_POS_X:    f32 = (WINDOW_WIDTH as f32) - PADDLE_PADDING - (PADDLE_WIDTH as f32);
 static bottom_start_pos_y: f32 = (WINDOW_HEIGHT as f32) - PADDLE_PADDING - (PADDLE_HEIGHT as f32);
 
-static PADDLE_VELOCITY: f32 = 5.;
+// Units per second (previously a flat per-frame offset); from_previous()
+// scales these by the fixed tick's dt before applying them.
+static PADDLE_VELOCITY: f32 = 5. * 60.;
 static UP_VECTOR:       Vector2f  = Vector2f { x:  0., y:  1. * PADDLE_VELOCITY };
 static DOWN_VECTOR:     Vector2f  = Vector2f { x:  0., y: -1. * PADDLE_VELOCITY };
 
@@ -32,10 +58,24 @@ static BALL_INITIAL_POSITION:  Vector2f = Vector2f {
     x: (WINDOW_WIDTH as f32) / 2.,
     y: (WINDOW_HEIGHT as f32)  / 2.
 };
-static BALL_VELOCITY:      f32 = 5.;
+// Units per second, scaling the random per-axis direction in velocity
+// (each component in [-1, 1], not normalized).
+static BALL_VELOCITY:      f32 = 5. * 60.;
 static BALL_FILL_COLOR:    Color = Color { red: 255, green: 0, blue: 0, alpha: 255 };
 static BALL_OUTLINE_COLOR: Color = Color { red: 255, green: 0, blue: 255, alpha: 255 };
 
+// HUD Defaults
+static HUD_FONT_PATH:      &'static str = "./assets/font.ttf";
+static HUD_CHARACTER_SIZE: uint = 24;
+static SCORE_POSITION:     Vector2f = Vector2f { x: 20., y: 20. };
+static MESSAGE_POSITION:   Vector2f = Vector2f {
+    x: (WINDOW_WIDTH as f32) / 2. - 100.,
+    y: 20.
+};
+static MESSAGE_DURATION_FRAMES: uint = 90;  // ~1.5s at 60fps.
+static HUD_TEXT_COLOR:    Color = Color { red: 0,   green: 0,   blue: 0,   alpha: 255 };
+static HUD_OUTLINE_COLOR: Color = Color { red: 255, green: 255, blue: 255, alpha: 255 };
+
 static START_POSITIONS: [Vector2f, ..4] = [
   Vector2f { x: LHS_START_POS_X, y: 0. + PADDLE_PADDING }, // Player 1
   Vector2f { x: RHS_START_POS_X, y: 0. + PADDLE_PADDING }, // Player 2
@@ -46,24 +86,123 @@ static START_POSITIONS: [Vector2f, ..4] = [
 #[deriving(Eq, Clone, IterBytes, FromPrimitive)]
 enum PlayerId {
   bluepaddle,
-  greenpaddle
+  greenpaddle,
+  redpaddle,
+  yellowpaddle
 }  // enum PlayerId
 
+// Styling for HUD text: a plain fill, or a fill plus an outline so text
+// stays legible over either paddle's color.
+enum TextMode {
+  PlainFill,
+  FillWithOutline
+}  // enum TextMode
+
+// Pure data the systems below read and write every tick. Position is the
+// one authoritative source of truth for where a paddle/the ball is in the
+// playfield; the rsfml Sprite/CircleShape a Paddle/Ball also owns is a
+// Renderable *view* onto it, synced by main()'s render loop once per
+// rendered frame, never the other way around -- no system does math against
+// paddle.sprite.get_position() anymore.
+struct Position {
+  value: Vector2f
+}  // struct Position
+
+struct Velocity {
+  value: Vector2f
+}  // struct Velocity
+
+// Anything that can be moved on screen to match a Position component --
+// implemented below for the two rsfml drawable types this game uses, so
+// main()'s render loop can treat paddles and the ball the same way.
+trait Renderable {
+  fn sync_position(&mut self, position: &Position);
+}  // trait Renderable
+
+impl<'r> Renderable for Sprite<'r> {
+  fn sync_position(&mut self, position: &Position) {
+    self.set_position(&position.value);
+  }  // fn sync_position()
+}  // impl Renderable for Sprite
+
+impl<'r> Renderable for CircleShape<'r> {
+  fn sync_position(&mut self, position: &Position) {
+    self.set_position(&position.value);
+  }  // fn sync_position()
+}  // impl Renderable for CircleShape
+
 struct Paddle<'r> {
-  sprite: Sprite<'r>
+  sprite: Sprite<'r>,
+  position: Position,
+  player_id: PlayerId
 }  // struct Paddle
 
 struct Ball<'r> {
   drawable: CircleShape<'r>,
-  velocity: Vector2f
+  position: Position,
+  velocity: Velocity
 }  // struct Ball
 
+// Generic, double-buffered event queue, modeled on the early bevy/legion
+// Events<T> resource: events pushed via send() land in `current`, and
+// become readable via iter() for exactly one frame once update() rotates
+// them into `previous`. This is what lets systems like "score changed" or
+// "ball reset" communicate without calling each other directly.
+struct Events<T> {
+  previous: ~[T],
+  current: ~[T]
+}  // struct Events
+
+impl<T> Events<T> {
+  fn new() -> Events<T> {
+    return Events { previous: ~[], current: ~[] };
+  }  // fn new()
+
+  fn send(&mut self, event: T) {
+    self.current.push(event);
+  }  // fn send()
+
+  // Rotates this frame's events into the readable buffer and starts a
+  // fresh `current` for the next frame. Call exactly once per frame.
+  fn update(&mut self) {
+    let mut next_current = ~[];
+    mem::swap(&mut self.current, &mut next_current);
+    self.previous = next_current;
+  }  // fn update()
+
+  fn iter<'a>(&'a self) -> std::vec::Items<'a, T> {
+    return self.previous.iter();
+  }  // fn iter()
+}  // impl Events
+
+// Decouples "a point was scored" (score_side(), from either the local or
+// networked game loop) from "what the HUD does about it" (message_system())
+// -- the two no longer need to call each other directly.
+enum GameEvent {
+  // The third field is ai.lua's on_score() return value (None for the
+  // default "Player N scores!" wording) -- carried on the event rather than
+  // written straight to state.message, since message_system() runs after
+  // score_side() in the same tick and would otherwise stomp a custom
+  // message with the generic one.
+  ScoreChanged(PlayerId, uint, Option<~str>),
+  BallReset
+}  // enum GameEvent
+
 struct PongGameState<'r> {
   window:  &'r mut RenderWindow,
   paddles: ~[Paddle<'r>],
   player_id: PlayerId,
   ball: Ball<'r>,
-  keys: ~[keyboard::Key]
+  keys: ~[keyboard::Key],
+  score: HashMap<PlayerId, uint>,
+  message: ~str,
+  message_timer: uint,
+  net_link: Option<net::ClientLink>,
+  arbiter: input::InputArbiter,
+  gamepads: Option<input::GamepadArbiter>,
+  events: Events<GameEvent>,
+  script: Option<scripting::ScriptEngine>,
+  ai_paddles: ~[PlayerId]
 }  // struct PongGameState
 
 impl<'r> PongGameState<'r> {
@@ -75,34 +214,426 @@ impl<'r> PongGameState<'r> {
         paddles: paddles_param,
         player_id: player_id_param,
         window: window_param,
-        ball: ball_param
+        ball: ball_param,
+        score: HashMap::new(),
+        message: ~"Press Space to serve",
+        message_timer: MESSAGE_DURATION_FRAMES,
+        net_link: None,
+        arbiter: input::InputArbiter::new_default(),
+        gamepads: None,
+        events: Events::new(),
+        script: None,
+        ai_paddles: ~[]
     };
   }  // fn new_default()
-  
-  // Construct a new state from an existing one.
-  fn from_previous(prev: PongGameState<'r>) -> PongGameState<'r> {
+
+  // Attaches this client to an authoritative net::spawn_server() link, so
+  // from_previous() applies server snapshots instead of integrating input
+  // and physics locally.
+  fn with_network(self, link: net::ClientLink) -> PongGameState<'r> {
+    let mut state = self;
+    state.net_link = Some(link);
+    return state;
+  }  // fn with_network()
+
+  // Attaches a gamepad backend so paddles other than `player_id` can be
+  // driven by a local controller instead of sitting uncontrollable.
+  fn with_gamepads(self, arbiter: input::GamepadArbiter) -> PongGameState<'r> {
+    let mut state = self;
+    state.gamepads = Some(arbiter);
+    return state;
+  }  // fn with_gamepads()
+
+  // Hands every paddle in `ai_paddles` over to `engine`'s on_tick(), so
+  // single-player mode doesn't require recompiling a new opponent.
+  fn with_ai(self, engine: scripting::ScriptEngine, ai_paddles: ~[PlayerId]) -> PongGameState<'r> {
+    let mut state = self;
+    state.script = Some(engine);
+    state.ai_paddles = ai_paddles;
+    return state;
+  }  // fn with_ai()
+
+  // Construct a new state from an existing one, advancing input/physics by
+  // exactly `dt` seconds. Called once per fixed tick from the accumulator
+  // loop in main(), never once per rendered frame, so gameplay speed does
+  // not depend on how fast the window is redrawn.
+  //
+  // Just an ordered dispatcher over the system functions below -- network
+  // input XOR local input/gamepad/AI/physics, then the frame-agnostic
+  // event-bus rotation and HUD reaction. Every system here reads/writes
+  // Position/Velocity components rather than paddle.sprite or
+  // ball.drawable directly; main()'s render loop is the only place those
+  // get synced back to the screen, once per rendered frame.
+  fn from_previous(prev: PongGameState<'r>, dt: f32) -> PongGameState<'r> {
     let mut state = prev;
-    let player_index: uint = state.player_id as uint;
-    { 
-      let player_paddle = &mut state.paddles[player_index];
-      for key in state.keys.iter() {
-        match *key {
-          keyboard::Escape => state.window.close(),
-          keyboard::K => { player_paddle.sprite.move(&UP_VECTOR); },
-          keyboard::J => { player_paddle.sprite.move(&DOWN_VECTOR); },
-          _ => {}
-        }
-      }
-    }
-    {
-      let velocity = state.ball.velocity * BALL_VELOCITY;
-      state.ball.drawable.move(&velocity);
+
+    if state.net_link.is_some() {
+      network_system(&mut state);
+    } else {
+      input_system(&mut state, dt);
+      gamepad_system(&mut state, dt);
+      ai_system(&mut state, dt);
+      step_physics(&mut state, dt);
     }
-    state.keys.clear();
+
+    state.events.update();
+    message_system(&mut state);
+
     return state;
   }  // fn from_previous()
 }  // impl PongGameState
 
+// Clamp a scalar to the inclusive range [lo, hi].
+fn clampf(v: f32, lo: f32, hi: f32) -> f32 {
+  if v < lo { lo } else if v > hi { hi } else { v }
+}  // fn clampf()
+
+// Scales a units-per-second vector down to this tick's displacement.
+fn scale_vector(v: Vector2f, dt: f32) -> Vector2f {
+  return Vector2f { x: v.x * dt, y: v.y * dt };
+}  // fn scale_vector()
+
+// Adds two vectors componentwise -- used to apply a scaled delta to a
+// Position component directly, the same thing Sprite::move() used to do
+// for whichever paddle a system moved.
+fn add_vector(a: Vector2f, b: Vector2f) -> Vector2f {
+  return Vector2f { x: a.x + b.x, y: a.y + b.y };
+}  // fn add_vector()
+
+// Blends from `a` to `b` by `t` (0 = a, 1 = b) -- used to render a position
+// between the last two fixed ticks rather than snapping straight to the
+// latest one, so motion doesn't judder when the render frame rate isn't an
+// exact multiple of TICK_RATE.
+fn lerp_vector(a: Vector2f, b: Vector2f, t: f32) -> Vector2f {
+  return Vector2f { x: a.x + (b.x - a.x) * t, y: a.y + (b.y - a.y) * t };
+}  // fn lerp_vector()
+
+// Human-readable label for a PlayerId, used in the HUD.
+fn player_label(id: PlayerId) -> ~str {
+  match id {
+    bluepaddle   => ~"Player 1",
+    greenpaddle  => ~"Player 2",
+    redpaddle    => ~"Player 3",
+    yellowpaddle => ~"Player 4"
+  }
+}  // fn player_label()
+
+// Which side of the net a PlayerId's paddle defends -- blue (top-left) and
+// red (bottom-left) share the left goal, green (top-right) and yellow
+// (bottom-right) share the right one, per START_POSITIONS above.
+#[deriving(Eq, Clone)]
+enum Side {
+  LeftSide,
+  RightSide
+}  // enum Side
+
+fn player_side(id: PlayerId) -> Side {
+  match id {
+    bluepaddle | redpaddle     => LeftSide,
+    greenpaddle | yellowpaddle => RightSide
+  }
+}  // fn player_side()
+
+// Records a point for every paddle defending `side` in the running score
+// HashMap and raises one ScoreChanged event -- what (if anything) happens
+// on screen because of it is message_system()'s concern, not this
+// function's. Crediting every paddle on the scoring side (rather than a
+// single hardcoded PlayerId) is what lets all four PlayerIds actually put
+// points on the board instead of just the two that used to be hardcoded.
+fn score_side(state: &mut PongGameState, side: Side) {
+  let scorers: ~[PlayerId] = state.paddles.iter()
+    .map(|paddle| paddle.player_id)
+    .filter(|id| player_side(*id) == side)
+    .collect();
+
+  for scorer in scorers.iter() {
+    let current_score = match state.score.find(scorer) {
+      Some(&existing) => existing,
+      None            => 0
+    };
+    state.score.insert(*scorer, current_score + 1);
+  }
+
+  // The event (and ai.lua's on_score() hook) only needs one representative
+  // PlayerId out of however many share this side -- the HUD message reads
+  // the same either way ("Player N scores!"), and the score HashMap update
+  // above already covers every paddle on the side.
+  match scorers.iter().next() {
+    Some(&primary) => {
+      let next_score = *state.score.find(&primary).unwrap();
+      let custom_message = match state.script {
+        Some(ref mut engine) => engine.on_score(primary),
+        None                 => None
+      };
+      state.events.send(ScoreChanged(primary, next_score, custom_message));
+    },
+    None => {}
+  }
+}  // fn score_side()
+
+// A random direction with each component drawn independently from [-1, 1].
+fn random_ball_velocity() -> Vector2f {
+  let mut rng = task_rng();
+  return Vector2f {
+      x: rng.gen_range::<f32>(-1., 1.),
+      y: rng.gen_range::<f32>(-1., 1.)
+  };
+}  // fn random_ball_velocity()
+
+// Put the ball back in the middle of the field with a fresh random velocity,
+// the same way create_ball() seeds the initial throw-off.
+fn reset_ball(ball: &mut Ball) {
+  ball.velocity = Velocity { value: random_ball_velocity() };
+  ball.position = Position { value: BALL_INITIAL_POSITION };
+}  // fn reset_ball()
+
+// Systems run in order each fixed tick by from_previous(): exactly one of
+// network_system() or {input_system, gamepad_system, ai_system,
+// step_physics()} runs depending on whether this client has a net_link,
+// then state.events.update() and message_system() always run. Every
+// system below reads/writes a Paddle's or the Ball's Position/Velocity
+// components -- rsfml's Sprite/CircleShape only gets touched again when
+// main()'s render loop syncs the (possibly interpolated) Position onto
+// it for drawing.
+
+// Networked system: send this tick's input out, then apply whatever
+// authoritative snapshot the server has sent back since last tick. The
+// server -- not this loop -- owns the Ball physics.
+fn network_system(state: &mut PongGameState) {
+  let player_id = state.player_id;
+  let keys = state.keys.clone();
+  match state.net_link {
+    Some(ref link) => {
+      link.outbound.send(net::InputMsg(net::Input { player_id: player_id, keys: keys }));
+      loop {
+        match link.inbound.try_recv() {
+          Some(net::SnapshotMsg(snapshot)) => {
+            for paddle in state.paddles.mut_iter() {
+              for &(ref id, position) in snapshot.paddles.iter() {
+                if *id == paddle.player_id {
+                  paddle.position = Position { value: position };
+                }
+              }
+            }
+            state.ball.velocity = Velocity { value: snapshot.ball_velocity };
+            state.ball.position = Position { value: snapshot.ball_position };
+            match snapshot.scoring_side {
+              Some(side) => score_side(state, side),
+              None       => {}
+            }
+          },
+          _ => break
+        }
+      }
+    },
+    None => {}
+  }
+  for key in state.keys.iter() {
+    match *key {
+      keyboard::Escape => state.window.close(),
+      _ => {}
+    }
+  }
+}  // fn network_system()
+
+// Local keyboard system: resolves this tick's held keys into Actions
+// through state.arbiter and moves the local player_id's own paddle.
+fn input_system(state: &mut PongGameState, dt: f32) {
+  let actions = state.arbiter.resolve(state.keys);
+  // Find the local player's own paddle by player_id rather than indexing
+  // state.paddles[state.player_id as uint] -- create_paddles() builds that
+  // array by zipping an unordered HashMap<PlayerId, Sprite> against
+  // START_POSITIONS, so array index has no guaranteed relationship to
+  // PlayerId. network_system()/gamepad_system()/ai_system() look paddles
+  // up this way too.
+  for paddle in state.paddles.mut_iter() {
+    if paddle.player_id != state.player_id { continue }
+    for action in actions.iter() {
+      match *action {
+        input::Quit     => state.window.close(),
+        input::MoveUp   => { paddle.position.value = add_vector(paddle.position.value, scale_vector(UP_VECTOR, dt)); },
+        input::MoveDown => { paddle.position.value = add_vector(paddle.position.value, scale_vector(DOWN_VECTOR, dt)); }
+      }
+    }
+  }
+}  // fn input_system()
+
+// Applies any gamepad-driven paddles -- additional local players beyond
+// the keyboard-controlled `player_id`.
+fn gamepad_system(state: &mut PongGameState, dt: f32) {
+  match state.gamepads {
+    Some(ref mut pads) => {
+      let pad_actions = pads.poll();
+      for paddle in state.paddles.mut_iter() {
+        if paddle.player_id == state.player_id { continue }
+        match pad_actions.find(&paddle.player_id) {
+          Some(paddle_actions) => {
+            if paddle_actions.contains(&input::Quit) { state.window.close(); }
+            paddle.position.value =
+              input::apply_actions(paddle.position.value, *paddle_actions, dt);
+          },
+          None => {}
+        }
+      }
+    },
+    None => {}
+  }
+}  // fn gamepad_system()
+
+// Drives any paddle listed in ai_paddles from the loaded ai.lua instead of
+// a local input device. on_tick() hands back a per-axis direction (like a
+// joystick axis, not a position), which is scaled by PADDLE_VELOCITY and
+// this tick's dt the same way keyboard and gamepad input are, then clamped
+// to the playfield so a buggy or malicious ai.lua can't teleport its
+// paddle off-screen.
+fn ai_system(state: &mut PongGameState, dt: f32) {
+  match state.script {
+    Some(ref mut engine) => {
+      let ball_position = state.ball.position.value;
+      let ball_velocity = state.ball.velocity.value;
+      for paddle in state.paddles.mut_iter() {
+        if !state.ai_paddles.contains(&paddle.player_id) { continue }
+        let position = paddle.position.value;
+        let direction = engine.on_tick(paddle.player_id, ball_position,
+          ball_velocity, position);
+        let delta = scale_vector(
+          Vector2f { x: direction.x * PADDLE_VELOCITY, y: direction.y * PADDLE_VELOCITY }, dt);
+        let target = Vector2f {
+          x: clampf(position.x + delta.x, 0., (WINDOW_WIDTH as f32) - (PADDLE_WIDTH as f32)),
+          y: clampf(position.y + delta.y, 0., (WINDOW_HEIGHT as f32) - (PADDLE_HEIGHT as f32))
+        };
+        paddle.position.value = target;
+      }
+    },
+    None => {}
+  }
+}  // fn ai_system()
+
+// Which edge the ball exited through, if any.
+#[deriving(Eq)]
+enum ExitSide {
+  LeftExit,
+  RightExit
+}  // enum ExitSide
+
+// Pure ball/paddle physics core: treats the ball as a circle (center =
+// position + (BALL_RADIUS, BALL_RADIUS)) and each paddle as an
+// axis-aligned rect at `paddle_positions[i]`, sized
+// PADDLE_WIDTH x PADDLE_HEIGHT. Bounces the ball off the top/bottom walls
+// and off paddles, and reports which edge (if any) the ball exited through
+// so the caller can score + re-serve. Takes and returns plain position/
+// velocity vectors (rather than &mut PongGameState) so it can run equally
+// from the local single-process loop in step_physics() and from the
+// headless authoritative loop that net::spawn_server() runs.
+fn simulate_ball(position: Vector2f, velocity: Vector2f, paddle_positions: &[Vector2f],
+    dt: f32) -> (Vector2f, Vector2f, Option<ExitSide>) {
+  let mut next = Vector2f {
+      x: position.x + velocity.x * BALL_VELOCITY * dt,
+      y: position.y + velocity.y * BALL_VELOCITY * dt
+  };
+  let mut next_velocity = velocity;
+
+  // Bounce off the top/bottom walls.
+  if next.y < 0. {
+    next_velocity.y = -next_velocity.y;
+    next.y = 0.;
+  } else if next.y + BALL_RADIUS * 2. > (WINDOW_HEIGHT as f32) {
+    next_velocity.y = -next_velocity.y;
+    next.y = (WINDOW_HEIGHT as f32) - BALL_RADIUS * 2.;
+  }
+
+  // Bounce off whichever paddle the ball's tentative position overlaps.
+  let center = Vector2f { x: next.x + BALL_RADIUS, y: next.y + BALL_RADIUS };
+  for paddle_pos in paddle_positions.iter() {
+    let clamped = Vector2f {
+        x: clampf(center.x, paddle_pos.x, paddle_pos.x + (PADDLE_WIDTH as f32)),
+        y: clampf(center.y, paddle_pos.y, paddle_pos.y + (PADDLE_HEIGHT as f32))
+    };
+    let dx = center.x - clamped.x;
+    let dy = center.y - clamped.y;
+    if dx * dx + dy * dy <= BALL_RADIUS * BALL_RADIUS {
+      next_velocity.x = -next_velocity.x;
+      // Push the ball back outside the paddle's face so it doesn't stick.
+      next.x = if center.x < paddle_pos.x + (PADDLE_WIDTH as f32) / 2. {
+        paddle_pos.x - BALL_RADIUS * 2.
+      } else {
+        paddle_pos.x + (PADDLE_WIDTH as f32)
+      };
+      // Add a little spin based on where along the paddle face it struck.
+      let hit_offset = (center.y - (paddle_pos.y + (PADDLE_HEIGHT as f32) / 2.))
+          / ((PADDLE_HEIGHT as f32) / 2.);
+      next_velocity.y += hit_offset * 0.5;
+      break;
+    }
+  }
+
+  // Report an edge exit instead of scoring directly -- the caller owns the
+  // score HashMap (and, over the network, which PlayerId sits on which
+  // side). Both edges use the same "ball has fully cleared the boundary"
+  // condition: the left edge has to clear x=0 and the right edge has to
+  // clear x=WINDOW_WIDTH, so neither side exits while any part of the
+  // ball is still on screen.
+  let left_edge  = next.x;
+  let right_edge = next.x + BALL_RADIUS * 2.;
+  if right_edge < 0. {
+    return (next, next_velocity, Some(LeftExit));
+  } else if left_edge > (WINDOW_WIDTH as f32) {
+    return (next, next_velocity, Some(RightExit));
+  }
+
+  return (next, next_velocity, None);
+}  // fn simulate_ball()
+
+// Physics/collision step run once per fixed tick from from_previous() for
+// the local (non-networked) game loop. Delegates the actual math to
+// simulate_ball() and applies the result to `state`.
+fn step_physics(state: &mut PongGameState, dt: f32) {
+  let paddle_positions: ~[Vector2f] = state.paddles.iter()
+    .map(|paddle| paddle.position.value).collect();
+  let position = state.ball.position.value;
+  let (next, next_velocity, exit) =
+    simulate_ball(position, state.ball.velocity.value, paddle_positions, dt);
+  state.ball.velocity = Velocity { value: next_velocity };
+
+  match exit {
+    Some(LeftExit) => {
+      score_side(state, RightSide);
+      reset_ball(&mut state.ball);
+      state.events.send(BallReset);
+    },
+    Some(RightExit) => {
+      score_side(state, LeftSide);
+      reset_ball(&mut state.ball);
+      state.events.send(BallReset);
+    },
+    None => { state.ball.position = Position { value: next }; }
+  }
+}  // fn step_physics()
+
+// System: turns this frame's GameEvents into the transient HUD message,
+// decoupled from whatever system raised them, then ticks the message's
+// remaining lifetime down.
+fn message_system(state: &mut PongGameState) {
+  for event in state.events.iter() {
+    match *event {
+      ScoreChanged(scorer, _, ref custom_message) => {
+        state.message = match *custom_message {
+          Some(ref message) => message.clone(),
+          None               => player_label(scorer) + " scores!"
+        };
+        state.message_timer = MESSAGE_DURATION_FRAMES;
+      },
+      BallReset => {}
+    }
+  }
+
+  if state.message_timer > 0 {
+    state.message_timer -= 1;
+  } else {
+    state.message = ~"";
+  }
+}  // fn message_system()
+
 // OSX Prevents creating a window on the main thread, so start up a new thread
 // and launch the window.
 #[start]
@@ -127,19 +658,16 @@ fn create_window() -> (RenderWindow, Color) {
 
 // Create a ball that's initialized with the values we declare statically.
 fn create_ball() -> Ball {
-  let mut rng = task_rng();
   let mut ball = Ball {
       drawable: CircleShape::new().expect("Could not instantiate ball"),
-      velocity: Vector2f {
-          x: rng.gen_range::<f32>(-1., 1.),
-          y: rng.gen_range::<f32>(-1., 1.)
-      }
+      position: Position { value: BALL_INITIAL_POSITION },
+      velocity: Velocity { value: Vector2f { x: 0., y: 0. } }
   };
   ball.drawable.set_radius(BALL_RADIUS);
   ball.drawable.set_outline_thickness(BALL_OUTLINE_THICKNESS);
   ball.drawable.set_fill_color(&BALL_FILL_COLOR);
   ball.drawable.set_outline_color(&BALL_OUTLINE_COLOR);
-  ball.drawable.set_position(&BALL_INITIAL_POSITION);
+  reset_ball(&mut ball);
 
   return ball;
 }  // fn create_ball()
@@ -173,23 +701,40 @@ fn create_paddles(sprites: HashMap<PlayerId, Sprite>) -> ~[Paddle] {
   let zipped = sprites.iter().zip(START_POSITIONS.iter());
 
   return zipped
-    .map(|((_, sprite_item), start_pos_item)| {
+    .map(|((player_id, sprite_item), _)| {
+      // Place each paddle by its PlayerId, not by HashMap iteration order.
+      let start_pos_item = &START_POSITIONS[*player_id as uint];
       let error_msg = "Error cloning sprite.";
       let mut paddle = Paddle {
         // todo: do I really have to clone? I just want to move the sprite's ...
-        sprite: sprite_item.clone().expect(error_msg)
+        sprite: sprite_item.clone().expect(error_msg),
+        position: Position { value: *start_pos_item },
+        player_id: player_id.clone()
       };
       paddle.sprite.set_position(start_pos_item);
-      return paddle; 
+      return paddle;
     }).collect();
 }  // fn create_paddles()
 
-// Loads the different textures as pairs with their corresponding PlayerId
-fn load_assets() -> HashMap<PlayerId, Texture> {
-  let dir               = "./assets/";
-  let blue_paddle_path  = dir + "blue-paddle.png";
-  let green_paddle_path = dir + "green-paddle.png";
-  let error_prefix      = "Could not load asset: ";
+// All the loaded assets the rest of the game borrows from for its lifetime:
+// the per-player paddle textures, and the HUD font. The font lives here
+// (rather than being loaded ad-hoc by the HUD) so it outlives every Text
+// that borrows it, the same lifetime pattern create_sprites() relies on for
+// Textures/Sprites.
+struct Assets {
+  textures: HashMap<PlayerId, Texture>,
+  font: Font
+}  // struct Assets
+
+// Loads the different textures as pairs with their corresponding PlayerId,
+// plus the HUD font.
+fn load_assets() -> Assets {
+  let dir                = "./assets/";
+  let blue_paddle_path   = dir + "blue-paddle.png";
+  let green_paddle_path  = dir + "green-paddle.png";
+  let red_paddle_path    = dir + "red-paddle.png";
+  let yellow_paddle_path = dir + "yellow-paddle.png";
+  let error_prefix       = "Could not load asset: ";
 
   let texture_rect = IntRect { left: 0, top: 0, width: PADDLE_WIDTH,
     height: PADDLE_HEIGHT };
@@ -199,12 +744,45 @@ fn load_assets() -> HashMap<PlayerId, Texture> {
   let green_paddle_texture = Texture::new_from_file_with_rect(
     green_paddle_path, &texture_rect).expect(error_prefix + green_paddle_path);
 
+  let red_paddle_texture = Texture::new_from_file_with_rect(
+    red_paddle_path, &texture_rect).expect(error_prefix + red_paddle_path);
+
+  let yellow_paddle_texture = Texture::new_from_file_with_rect(
+    yellow_paddle_path, &texture_rect).expect(error_prefix + yellow_paddle_path);
+
   let mut hs = HashMap::new();
   hs.insert(bluepaddle, blue_paddle_texture);
   hs.insert(greenpaddle, green_paddle_texture);
-  return hs;
+  hs.insert(redpaddle, red_paddle_texture);
+  hs.insert(yellowpaddle, yellow_paddle_texture);
+
+  let font = Font::new_from_file(HUD_FONT_PATH)
+    .expect(error_prefix + HUD_FONT_PATH);
+
+  return Assets { textures: hs, font: font };
 } // fn load_assets
 
+// Builds a positioned, styled Text borrowing `font`. Mirrors create_sprites()
+// in taking the asset by reference and tying the returned value's lifetime
+// to it.
+fn draw_text<'r>(font: &'r Font, text_str: &str, size: uint, position: &Vector2f,
+    mode: TextMode) -> Text<'r> {
+  let mut text = Text::new().expect("Could not instantiate text");
+  text.set_font(font);
+  text.set_character_size(size as u32);
+  text.set_string(text_str);
+  text.set_position(position);
+  text.set_color(&HUD_TEXT_COLOR);
+  match mode {
+    PlainFill        => {},
+    FillWithOutline => {
+      text.set_outline_color(&HUD_OUTLINE_COLOR);
+      text.set_outline_thickness(2.);
+    }
+  }
+  return text;
+}  // fn draw_text()
+
 // The scan fn below returns an iterator that will iterator over the sprite's'
   // in assets HashMap.
   // The initial state of scan() is the sprites iterator.
@@ -221,26 +799,391 @@ fn load_assets() -> HashMap<PlayerId, Texture> {
 
 
 
+// Pluggable input layer: translates raw device events (keyboard, gamepad)
+// into the abstract Actions the rest of the game reacts to, through a
+// user-editable binding table. This is what lets from_previous() stop
+// caring whether a given PlayerId's paddle is driven by J/K/Escape, a
+// remapped key, or a gamepad axis.
+mod input {
+  use super::{PlayerId, Vector2f, UP_VECTOR, DOWN_VECTOR};
+  use rsfml::window::keyboard;
+  use std::hashmap::HashMap;
+
+  // The abstract actions every input backend is translated down to.
+  #[deriving(Eq, Clone, IterBytes)]
+  pub enum Action {
+    MoveUp,
+    MoveDown,
+    Quit
+  }  // enum Action
+
+  // A user-editable table mapping raw keyboard keys to abstract actions.
+  pub struct InputArbiter {
+    bindings: HashMap<keyboard::Key, Action>
+  }  // struct InputArbiter
+
+  impl InputArbiter {
+    // The J/K/Escape bindings previously hardcoded in from_previous().
+    pub fn new_default() -> InputArbiter {
+      let mut bindings = HashMap::new();
+      bindings.insert(keyboard::K, MoveUp);
+      bindings.insert(keyboard::J, MoveDown);
+      bindings.insert(keyboard::Escape, Quit);
+      return InputArbiter { bindings: bindings };
+    }  // fn new_default()
+
+    // Re-maps one binding, e.g. to support a user's custom key layout.
+    pub fn rebind(&mut self, key: keyboard::Key, action: Action) {
+      self.bindings.insert(key, action);
+    }  // fn rebind()
+
+    // Translates pressed keys into the actions bound to them, dropping
+    // any key this arbiter has no binding for.
+    pub fn resolve(&self, keys: &[keyboard::Key]) -> ~[Action] {
+      return keys.iter()
+        .filter_map(|key| self.bindings.find(key).map(|action| action.clone()))
+        .collect();
+    }  // fn resolve()
+  }  // impl InputArbiter
+
+  // Moves a paddle position according to already-resolved actions -- the
+  // same headless movement net::apply_input() does for networked paddles,
+  // shared here with the gamepad backend below.
+  pub fn apply_actions(position: Vector2f, actions: &[Action], dt: f32) -> Vector2f {
+    let mut next = position;
+    for action in actions.iter() {
+      match *action {
+        MoveUp   => { let v = super::scale_vector(UP_VECTOR, dt);   next.x += v.x; next.y += v.y; },
+        MoveDown => { let v = super::scale_vector(DOWN_VECTOR, dt); next.x += v.x; next.y += v.y; },
+        Quit     => {}
+      }
+    }
+    return next;
+  }  // fn apply_actions()
+
+  // Gamepad backend: polls gilrs for button/axis events and translates
+  // them into the same Action stream the keyboard backend produces, one
+  // binding table per connected gamepad's assigned PlayerId. This is what
+  // lets each of the four START_POSITIONS slots be a local player on its
+  // own controller instead of only `player_id` being controllable.
+  pub struct GamepadArbiter {
+    gilrs: gilrs::Gilrs,
+    assignments: HashMap<gilrs::GamepadId, PlayerId>
+  }  // struct GamepadArbiter
+
+  impl GamepadArbiter {
+    // Connects to every gamepad gilrs can see and assigns them to
+    // PlayerIds in controller order (first pad -> first PlayerId, ...).
+    pub fn new_default(player_ids: &[PlayerId]) -> GamepadArbiter {
+      let gilrs = gilrs::Gilrs::new().expect("Could not initialize gilrs");
+      let mut assignments = HashMap::new();
+      for (gamepad_id, player_id) in gilrs.gamepads().zip(player_ids.iter()) {
+        assignments.insert(gamepad_id, player_id.clone());
+      }
+      return GamepadArbiter { gilrs: gilrs, assignments: assignments };
+    }  // fn new_default()
+
+    // Drains gilrs's event queue and returns the actions each assigned
+    // PlayerId should apply this tick.
+    pub fn poll(&mut self) -> HashMap<PlayerId, ~[Action]> {
+      let mut out: HashMap<PlayerId, ~[Action]> = HashMap::new();
+      loop {
+        match self.gilrs.next_event() {
+          Some((gamepad_id, event)) => {
+            let player_id = match self.assignments.find(&gamepad_id) {
+              Some(id) => id.clone(),
+              None     => continue
+            };
+            let resolved = match event {
+              gilrs::ButtonPressed(gilrs::DPadUp)   => Some(MoveUp),
+              gilrs::ButtonPressed(gilrs::DPadDown) => Some(MoveDown),
+              gilrs::ButtonPressed(gilrs::South)    => Some(Quit),
+              gilrs::AxisChanged(gilrs::LeftStickY, value) if value > 0.5  => Some(MoveUp),
+              gilrs::AxisChanged(gilrs::LeftStickY, value) if value < -0.5 => Some(MoveDown),
+              _ => None
+            };
+            match resolved {
+              Some(action) => { out.find_or_insert(player_id, ~[]).push(action); },
+              None          => {}
+            }
+          },
+          None => break
+        }
+      }
+      return out;
+    }  // fn poll()
+  }  // impl GamepadArbiter
+}  // mod input
+
+// Optional Lua scripting subsystem, gated behind the "scripting" feature
+// the way doukutsu-rs gates its scripting/text_script modules. Scripts read
+// the ball's position/velocity and a paddle's own position, and return a
+// target direction for any non-human PlayerId (integrated through
+// scale_vector() by the AI system the same way keyboard/gamepad input is,
+// not teleported to directly), so shipping a new opponent (track-the-ball,
+// predictive interception, ...) is a matter of dropping in a new ai.lua
+// rather than recompiling. Both cfg variants below expose the same API so
+// call sites never need their own #[cfg].
+#[cfg(feature = "scripting")]
+mod scripting {
+  use super::{PlayerId, Vector2f};
+  use lua;
+
+  // Wraps one loaded ai.lua and the Lua state backing it.
+  pub struct ScriptEngine {
+    lua: lua::Lua
+  }  // struct ScriptEngine
+
+  impl ScriptEngine {
+    pub fn load(path: &str) -> ScriptEngine {
+      let mut lua = lua::Lua::new();
+      lua.open_libs();
+      lua.do_file(path).expect("Could not load AI script: " + path);
+      return ScriptEngine { lua: lua };
+    }  // fn load()
+
+    // Calls the script's on_tick(player_id, ball_x, ball_y, ball_vx,
+    // ball_vy, paddle_x, paddle_y) and reads back the (x, y) it pushes as
+    // this paddle's movement direction for the tick -- a joystick-axis-like
+    // value in roughly [-1, 1] per component, not an absolute position.
+    pub fn on_tick(&mut self, player_id: PlayerId, ball_position: Vector2f,
+        ball_velocity: Vector2f, paddle_position: Vector2f) -> Vector2f {
+      self.lua.get_global("on_tick");
+      self.lua.push_int(player_id as int);
+      self.lua.push_number(ball_position.x as f64);
+      self.lua.push_number(ball_position.y as f64);
+      self.lua.push_number(ball_velocity.x as f64);
+      self.lua.push_number(ball_velocity.y as f64);
+      self.lua.push_number(paddle_position.x as f64);
+      self.lua.push_number(paddle_position.y as f64);
+      self.lua.call(7, 2);
+      let direction_y = self.lua.to_number(-1) as f32;
+      let direction_x = self.lua.to_number(-2) as f32;
+      self.lua.pop(2);
+      return Vector2f { x: direction_x, y: direction_y };
+    }  // fn on_tick()
+
+    // Calls the script's on_score(scorer_player_id) and reads back an
+    // optional custom HUD message (or nil to keep the default "Player N
+    // scores!" one) -- the return path that makes on_score() able to
+    // actually implement rule customization (serve announcements, win
+    // conditions, ...) instead of just notifying the script after the fact.
+    pub fn on_score(&mut self, scorer: PlayerId) -> Option<~str> {
+      self.lua.get_global("on_score");
+      self.lua.push_int(scorer as int);
+      self.lua.call(1, 1);
+      let message = if self.lua.is_nil(-1) { None } else { Some(self.lua.to_str(-1).to_owned()) };
+      self.lua.pop(1);
+      return message;
+    }  // fn on_score()
+  }  // impl ScriptEngine
+}  // mod scripting
+
+// Stub used when the "scripting" feature is off: same API, but AI paddles
+// report a zero direction (so they simply hold position once integrated)
+// and on_score() supplies no custom message.
+#[cfg(not(feature = "scripting"))]
+mod scripting {
+  use super::{PlayerId, Vector2f};
+
+  pub struct ScriptEngine;
+
+  impl ScriptEngine {
+    pub fn load(_path: &str) -> ScriptEngine { ScriptEngine }
+
+    pub fn on_tick(&mut self, _player_id: PlayerId, _ball_position: Vector2f,
+        _ball_velocity: Vector2f, _paddle_position: Vector2f) -> Vector2f {
+      return Vector2f { x: 0., y: 0. };
+    }  // fn on_tick()
+
+    pub fn on_score(&mut self, _scorer: PlayerId) -> Option<~str> { None }  // fn on_score()
+  }  // impl ScriptEngine
+}  // mod scripting
+
+// Minimal client/server networking subsystem: the server owns the Ball
+// physics and every paddle's position (reusing simulate_ball(), the same
+// core step_physics() runs locally), each client sends only the
+// keyboard::Key set it pressed since the last tick, and the server
+// broadcasts the resulting (PlayerId, Vector2f) paddle positions plus the
+// ball's position/velocity back out every tick.
+//
+// The transport here is an in-process Chan/Port pair per client, the same
+// Sender/Receiver-per-task model the servo canvas task uses -- swapping in
+// a real socket transport underneath ClientLink is future work, but the
+// message shapes and the authoritative server loop are the real thing
+// remote clients would speak to.
+mod net {
+  use super::{PlayerId, ExitSide, LeftExit, RightExit, simulate_ball,
+    random_ball_velocity, BALL_INITIAL_POSITION, UP_VECTOR, DOWN_VECTOR,
+    scale_vector, FIXED_DT, Side, LeftSide, RightSide};
+  use rsfml::window::keyboard;
+  use rsfml::system::vector2::Vector2f;
+  use std::comm::{Port, Chan, stream};
+  use std::io::timer::Timer;
+  use std::task;
+
+  // Sent client -> server once per tick: the keys pressed since last send.
+  pub struct Input {
+    pub player_id: PlayerId,
+    pub keys: ~[keyboard::Key]
+  }  // struct Input
+
+  // Sent server -> every client once per tick: the authoritative world
+  // state, plus who (if anyone) scored this tick.
+  pub struct Snapshot {
+    pub paddles: ~[(PlayerId, Vector2f)],
+    pub ball_position: Vector2f,
+    pub ball_velocity: Vector2f,
+    pub scoring_side: Option<Side>
+  }  // struct Snapshot
+
+  pub enum Msg {
+    InputMsg(Input),
+    SnapshotMsg(Snapshot)
+  }  // enum Msg
+
+  // One client's link to the authoritative server.
+  pub struct ClientLink {
+    pub outbound: Chan<Msg>,
+    pub inbound: Port<Msg>
+  }  // struct ClientLink
+
+  // Applies a client's pressed keys to a paddle's position -- the headless
+  // equivalent of the Position updates input_system() makes locally.
+  fn apply_input(position: Vector2f, keys: &[keyboard::Key], dt: f32) -> Vector2f {
+    let mut next = position;
+    for key in keys.iter() {
+      match *key {
+        keyboard::K => { let v = scale_vector(UP_VECTOR, dt);   next.x += v.x; next.y += v.y; },
+        keyboard::J => { let v = scale_vector(DOWN_VECTOR, dt); next.x += v.x; next.y += v.y; },
+        _ => {}
+      }
+    }
+    return next;
+  }  // fn apply_input()
+
+  // Spawns the authoritative server task and returns one ClientLink per
+  // entry in `player_ids`/`start_positions` (zipped pairwise). Two-to-four
+  // machines each hold one of the returned links and talk only through it.
+  pub fn spawn_server(player_ids: ~[PlayerId], start_positions: ~[Vector2f])
+      -> ~[ClientLink] {
+    let mut server_ports = ~[];  // (PlayerId, start, Port<Msg>, Chan<Msg>) the server owns
+    let mut client_links = ~[];
+
+    for (id, start) in player_ids.iter().zip(start_positions.iter()) {
+      let (server_inbound, client_outbound) = stream::<Msg>();
+      let (client_inbound, server_outbound) = stream::<Msg>();
+      server_ports.push((id.clone(), *start, server_inbound, server_outbound));
+      client_links.push(ClientLink { outbound: client_outbound, inbound: client_inbound });
+    }
+
+    task::spawn(proc() {
+      let mut positions: ~[(PlayerId, Vector2f)] = server_ports.iter()
+        .map(|&(ref id, start, _, _)| (id.clone(), start)).collect();
+      let mut ball_position = BALL_INITIAL_POSITION;
+      let mut ball_velocity = random_ball_velocity();
+
+      // Paces the authoritative loop against FIXED_DT instead of spinning a
+      // core as fast as try_recv() can be polled, so the server simulates
+      // at the same tick rate the fixed-timestep client in main() targets.
+      let mut timer = match Timer::new() {
+        Ok(timer) => timer,
+        Err(e)    => fail!("Could not create Timer: {}", e)
+      };
+
+      loop {
+        for &(ref id, _, ref inbound, _) in server_ports.iter() {
+          loop {
+            match inbound.try_recv() {
+              Some(InputMsg(input)) => {
+                for pair in positions.mut_iter() {
+                  let (ref pid, ref mut position) = *pair;
+                  if pid == id {
+                    *position = apply_input(*position, input.keys, FIXED_DT);
+                  }
+                }
+              },
+              _ => break
+            }
+          }
+        }
+
+        let paddle_positions: ~[Vector2f] =
+          positions.iter().map(|&(_, position)| position).collect();
+        let (next_ball, next_velocity, exit) =
+          simulate_ball(ball_position, ball_velocity, paddle_positions, FIXED_DT);
+
+        // A LeftExit means the ball got past the left goal, so the right
+        // side scores, and vice versa -- same side<->exit mapping
+        // step_physics() uses locally, so a networked game scores
+        // identically to a local one.
+        let scoring_side = match exit {
+          Some(LeftExit)  => Some(RightSide),
+          Some(RightExit) => Some(LeftSide),
+          None            => None
+        };
+        match scoring_side {
+          Some(_) => {
+            ball_position = BALL_INITIAL_POSITION;
+            ball_velocity = random_ball_velocity();
+          },
+          None => {
+            ball_position = next_ball;
+            ball_velocity = next_velocity;
+          }
+        }
+
+        for &(_, _, _, ref outbound) in server_ports.iter() {
+          let snapshot_scoring_side = match scoring_side {
+            Some(ref s) => Some(s.clone()),
+            None        => None
+          };
+          outbound.send(SnapshotMsg(Snapshot {
+            paddles: positions.iter().map(|&(ref id, position)| (id.clone(), position)).collect(),
+            ball_position: ball_position,
+            ball_velocity: ball_velocity,
+            scoring_side: snapshot_scoring_side
+          }));
+        }
+
+        timer.sleep((FIXED_DT * 1000.) as u64);
+      }
+    });
+
+    return client_links;
+  }  // fn spawn_server()
+}  // mod net
+
 // Loop forever polling events from the window, until there are no more events.
-// When there are no more events, break out of the loop.
-fn loop_events<'r>(prev: PongGameState<'r>) -> PongGameState<'r> {
-  let mut state = PongGameState::from_previous(prev);
+// When there are no more events, break out of the loop. Pure window/input
+// polling now -- advancing game state is the accumulator loop's job in
+// main(), run in fixed FIXED_DT increments rather than once per call here.
+//
+// `state.keys` is cleared once here, per rendered frame, rather than once
+// per fixed tick: the accumulator loop below may run zero, one, or several
+// from_previous() ticks for a single frame's worth of polled input, and
+// every one of those ticks needs to see the same held-down keys. Clearing
+// it inside from_previous() (as a prior version of this function did)
+// meant a slow/catch-up frame running more than one tick would consume and
+// drop the keys on all but the first tick.
+fn poll_window_events<'r>(mut state: PongGameState<'r>) -> PongGameState<'r> {
+  state.keys.clear();
   loop {
     match state.window.poll_event() {
-      event::Closed               => state.window.close(), 
+      event::Closed               => state.window.close(),
       event::KeyPressed{code, ..} => { state.keys.push(code); },
       _                           => break  // Maybe have to do event::NoEvent
     }
   }
   return state;
-}  // fn loop_events()
+}  // fn poll_window_events()
 
 // Entry point for pong
 fn main() {
   let (mut window, clear_color) = create_window();
   let assets = load_assets();
 
-  let sprites = create_sprites(&assets);
+  let sprites = create_sprites(&assets.textures);
   let paddles = create_paddles(sprites);
 
   let ball = create_ball();
@@ -250,16 +1193,182 @@ fn main() {
   let player_id = FromPrimitive::from_int(0).expect("PlayerId");
   let mut state = PongGameState::new_default(paddles, &mut window, player_id, ball);
 
+  // NETWORKED_PLAY (below) picks this client's link off the authoritative
+  // server's ~[ClientLink] -- one entry per PlayerId/START_POSITIONS slot.
+  // Every other link would be handed to the other 1-3 machines sharing this
+  // game; here there's only this one process, so they're simply dropped.
+  if NETWORKED_PLAY {
+    let mut links = net::spawn_server(~[bluepaddle, greenpaddle, redpaddle, yellowpaddle],
+      ~[START_POSITIONS[0], START_POSITIONS[1], START_POSITIONS[2], START_POSITIONS[3]]);
+    let my_link = links.remove(player_id as uint);
+    state = state.with_network(my_link);
+  }
+
+  // Hand every non-keyboard paddle a gamepad, in controller order, so up
+  // to three local players can each use their own pad alongside the
+  // keyboard. `player_id` is excluded here -- including it would assign
+  // the first physical controller to the same PlayerId the keyboard
+  // already drives, and gamepad_system()'s `paddle.player_id ==
+  // state.player_id` guard would then silently drop that controller's
+  // input (including Quit) every tick.
+  let gamepad_player_ids: [PlayerId, ..3] = match player_id {
+    bluepaddle   => [greenpaddle, redpaddle, yellowpaddle],
+    greenpaddle  => [bluepaddle, redpaddle, yellowpaddle],
+    redpaddle    => [bluepaddle, greenpaddle, yellowpaddle],
+    yellowpaddle => [bluepaddle, greenpaddle, redpaddle]
+  };
+  state = state.with_gamepads(input::GamepadArbiter::new_default(gamepad_player_ids));
+
+  // Let ai.lua play the opponent instead of leaving it uncontrolled, for
+  // single-player mode. Requires the "scripting" feature.
+  if AI_ENABLED {
+    let opponent = if player_id == bluepaddle { greenpaddle } else { bluepaddle };
+    state = state.with_ai(scripting::ScriptEngine::load(AI_SCRIPT_PATH), ~[opponent]);
+  }
+
+  // Fixed-timestep accumulator: real elapsed time per rendered frame is
+  // clamped (a paused/slow frame can't cause a catch-up spiral) then drained
+  // in FIXED_DT-sized bites, so from_previous() always integrates physics
+  // and input by the same dt regardless of how fast frames are rendering.
+  let mut clock = Clock::new().expect("Clock::new");
+  let mut accumulator: f32 = 0.;
+
   // when I press the 'j | k' keys, move the first paddle..
   while state.window.is_open() {
     state.window.clear(&clear_color);
-    state = loop_events(state); 
-    // update_state
+    state = poll_window_events(state);
 
-    for paddle in state.paddles.iter() {
-      state.window.draw(&state.ball.drawable);
+    // Snapshot the positions this frame starts from so they can be blended
+    // against the post-tick positions below -- the interpolation source.
+    // Read straight off the Position components rather than the
+    // Sprite/CircleShape, since those are the authoritative values now.
+    let prev_ball_position = state.ball.position.value;
+    let prev_paddle_positions: ~[Vector2f] = state.paddles.iter()
+      .map(|paddle| paddle.position.value).collect();
+
+    let frame_time = clampf(clock.restart().as_seconds(), 0., MAX_FRAME_TIME);
+    accumulator += frame_time;
+    while accumulator >= FIXED_DT {
+      state = PongGameState::from_previous(state, FIXED_DT);
+      accumulator -= FIXED_DT;
+    }
+
+    // Whatever's left in `accumulator` is the fraction of a tick not yet
+    // simulated; render at the position that fraction of the way from last
+    // tick's result to this tick's, instead of snapping straight to the
+    // latest tick, so motion stays smooth between ticks.
+    let alpha = accumulator / FIXED_DT;
+
+    // Sync each Renderable to an interpolated draw position -- this never
+    // touches the authoritative Position component itself, so there's
+    // nothing to restore afterward the way the old sprite-is-the-source-
+    // of-truth code had to.
+    let ball_draw_position = Position {
+      value: lerp_vector(prev_ball_position, state.ball.position.value, alpha)
+    };
+    state.ball.drawable.sync_position(&ball_draw_position);
+    state.window.draw(&state.ball.drawable);
+
+    for (index, paddle) in state.paddles.mut_iter().enumerate() {
+      let draw_position = Position {
+        value: lerp_vector(prev_paddle_positions[index], paddle.position.value, alpha)
+      };
+      paddle.sprite.sync_position(&draw_position);
       state.window.draw(&paddle.sprite);
     }
+
+    // HUD: current score for each player, plus any transient message.
+    let score_line = state.paddles.iter().enumerate()
+      .map(|(index, _)| {
+        let id: PlayerId = FromPrimitive::from_uint(index).expect("PlayerId");
+        let score = match state.score.find(&id) {
+          Some(&existing) => existing,
+          None            => 0
+        };
+        player_label(id) + ": " + score.to_str()
+      })
+      .collect::<~[~str]>()
+      .connect("   ");
+    let score_text = draw_text(&assets.font, score_line, HUD_CHARACTER_SIZE,
+      &SCORE_POSITION, PlainFill);
+    state.window.draw(&score_text);
+
+    if state.message.len() > 0 {
+      let message_text = draw_text(&assets.font, state.message, HUD_CHARACTER_SIZE,
+        &MESSAGE_POSITION, FillWithOutline);
+      state.window.draw(&message_text);
+    }
+
     state.window.display();
   }
 }  // fn main()
+
+// simulate_ball() is a pure function with no rsfml/window dependency, so
+// it's worth covering directly rather than only exercising it by hand
+// through a running window.
+#[cfg(test)]
+mod tests {
+  use super::{simulate_ball, Vector2f, LeftExit, RightExit, BALL_RADIUS, WINDOW_WIDTH,
+    PADDLE_WIDTH, FIXED_DT};
+
+  #[test]
+  fn bounces_off_the_top_wall() {
+    let position = Vector2f { x: 500., y: 1. };
+    let velocity = Vector2f { x: 0., y: -1. };
+    let no_paddles: [Vector2f, ..0] = [];
+    let (next, next_velocity, exit) = simulate_ball(position, velocity, no_paddles, FIXED_DT);
+
+    assert!(exit.is_none());
+    assert_eq!(next.y, 0.);
+    assert_eq!(next_velocity.y, 1.);
+  }  // fn bounces_off_the_top_wall()
+
+  #[test]
+  fn bounces_off_a_paddle_and_reverses_x_velocity() {
+    // Struck dead center vertically, so there should be no added spin.
+    let position = Vector2f { x: 25., y: 315. };
+    let velocity = Vector2f { x: -1., y: 0. };
+    let paddle_positions = [Vector2f { x: 0., y: 300. }];
+    let (next, next_velocity, exit) =
+      simulate_ball(position, velocity, paddle_positions, FIXED_DT);
+
+    assert!(exit.is_none());
+    assert_eq!(next_velocity.x, 1.);
+    assert_eq!(next_velocity.y, 0.);
+    assert_eq!(next.x, PADDLE_WIDTH as f32);
+  }  // fn bounces_off_a_paddle_and_reverses_x_velocity()
+
+  #[test]
+  fn adds_spin_based_on_where_the_ball_struck_the_paddle() {
+    // Struck near the paddle's top edge rather than dead center, so the
+    // ball should pick up some upward (negative) spin.
+    let position = Vector2f { x: 25., y: 292. };
+    let velocity = Vector2f { x: -1., y: 0. };
+    let paddle_positions = [Vector2f { x: 0., y: 300. }];
+    let (_, next_velocity, exit) =
+      simulate_ball(position, velocity, paddle_positions, FIXED_DT);
+
+    assert!(exit.is_none());
+    assert!(next_velocity.y < 0.);
+  }  // fn adds_spin_based_on_where_the_ball_struck_the_paddle()
+
+  #[test]
+  fn reports_a_left_exit_once_the_ball_fully_clears_the_left_wall() {
+    let position = Vector2f { x: -(BALL_RADIUS * 2.) - 1., y: 300. };
+    let velocity = Vector2f { x: 0., y: 0. };
+    let no_paddles: [Vector2f, ..0] = [];
+    let (_, _, exit) = simulate_ball(position, velocity, no_paddles, FIXED_DT);
+
+    assert!(exit == Some(LeftExit));
+  }  // fn reports_a_left_exit_once_the_ball_fully_clears_the_left_wall()
+
+  #[test]
+  fn reports_a_right_exit_once_the_ball_fully_clears_the_right_wall() {
+    let position = Vector2f { x: (WINDOW_WIDTH as f32) + 1., y: 300. };
+    let velocity = Vector2f { x: 0., y: 0. };
+    let no_paddles: [Vector2f, ..0] = [];
+    let (_, _, exit) = simulate_ball(position, velocity, no_paddles, FIXED_DT);
+
+    assert!(exit == Some(RightExit));
+  }  // fn reports_a_right_exit_once_the_ball_fully_clears_the_right_wall()
+}  // mod tests